@@ -0,0 +1,152 @@
+//! Detects ROA conflicts and redundancy before the set gets emitted: duplicate
+//! entries, a prefix announced under more than one origin, and a more-specific
+//! prefix whose origin differs from a covering ROA's origin.
+
+use std::{collections::HashMap, net::IpAddr};
+
+use crate::ROA;
+
+pub(crate) struct ConflictReport {
+    pub(crate) duplicates: usize,
+    pub(crate) conflicts: usize,
+}
+
+struct Node {
+    children: [Option<Box<Node>>; 2],
+    asn: Option<String>,
+}
+
+impl Node {
+    fn new() -> Node {
+        Node {
+            children: [None, None],
+            asn: None,
+        }
+    }
+}
+
+/// Scans the aggregated ROA set and prints a warning per problem found to stderr.
+/// Assumes `roas` is sorted (as `build_roas` returns it), so duplicates are adjacent.
+pub(crate) fn analyze(roas: &[ROA]) -> ConflictReport {
+    let mut duplicates = 0;
+
+    for pair in roas.windows(2) {
+        if pair[0] == pair[1] {
+            duplicates += 1;
+            eprintln!(
+                "warning: duplicate ROA: {} max {} as {}",
+                pair[0].prefix, pair[0].max_length, pair[0].asn
+            );
+        }
+    }
+
+    let mut conflicts = 0;
+
+    let mut origins_by_prefix: HashMap<&str, Vec<&str>> = HashMap::new();
+    for roa in roas {
+        let origins = origins_by_prefix.entry(roa.prefix.as_str()).or_default();
+        if !origins.contains(&roa.asn.as_str()) {
+            origins.push(roa.asn.as_str());
+        }
+    }
+
+    for (prefix, origins) in &origins_by_prefix {
+        if origins.len() > 1 {
+            conflicts += 1;
+            eprintln!(
+                "warning: conflict: {prefix} is announced under multiple origins: {}",
+                origins.join(", ")
+            );
+        }
+    }
+
+    let mut v4 = Node::new();
+    let mut v6 = Node::new();
+
+    for roa in roas {
+        let Some((addr, netmask)) = parse_prefix(&roa.prefix) else { continue };
+
+        match addr {
+            IpAddr::V4(addr) => {
+                let bits = u32::from(addr);
+                insert(&mut v4, netmask, &roa.asn, |i| (bits >> (31 - i)) & 1 == 1);
+            },
+            IpAddr::V6(addr) => {
+                let bits = u128::from(addr);
+                insert(&mut v6, netmask, &roa.asn, |i| (bits >> (127 - i)) & 1 == 1);
+            },
+        }
+    }
+
+    for roa in roas {
+        let Some((addr, netmask)) = parse_prefix(&roa.prefix) else { continue };
+
+        let covering = match addr {
+            IpAddr::V4(addr) => {
+                let bits = u32::from(addr);
+                find_covering_asn(&v4, netmask, |i| (bits >> (31 - i)) & 1 == 1)
+            },
+            IpAddr::V6(addr) => {
+                let bits = u128::from(addr);
+                find_covering_asn(&v6, netmask, |i| (bits >> (127 - i)) & 1 == 1)
+            },
+        };
+
+        if let Some(covering_asn) = covering && covering_asn != roa.asn {
+            conflicts += 1;
+            eprintln!(
+                "warning: conflict: {} as {} is a more specific prefix not covered by its covering ROA's origin ({covering_asn})",
+                roa.prefix, roa.asn
+            );
+        }
+    }
+
+    ConflictReport { duplicates, conflicts }
+}
+
+fn parse_prefix(prefix: &str) -> Option<(IpAddr, u8)> {
+    let parts: Vec<_> = prefix.split('/').collect();
+    if parts.len() != 2 {
+        return None;
+    }
+
+    let addr: IpAddr = parts[0].parse().ok()?;
+    let netmask: u8 = parts[1].parse().ok()?;
+
+    Some((addr, netmask))
+}
+
+fn insert(root: &mut Node, netmask: u8, asn: &str, bit_at: impl Fn(u8) -> bool) {
+    let mut node = root;
+
+    for i in 0..netmask {
+        let bit = bit_at(i) as usize;
+        node = node.children[bit].get_or_insert_with(|| Box::new(Node::new()));
+    }
+
+    node.asn = Some(asn.to_owned());
+}
+
+fn find_covering_asn(root: &Node, netmask: u8, bit_at: impl Fn(u8) -> bool) -> Option<&str> {
+    if netmask == 0 {
+        return None;
+    }
+
+    let mut node = root;
+    let mut best = node.asn.as_deref();
+
+    for i in 0..netmask - 1 {
+        let bit = bit_at(i) as usize;
+
+        node = match &node.children[bit] {
+            Some(next) => next,
+            None => break,
+        };
+
+        if let Some(asn) = &node.asn {
+            best = Some(asn.as_str());
+        }
+    }
+
+    best
+}
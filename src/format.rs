@@ -0,0 +1,93 @@
+//! Output formats for the generated ROA set, selected with `--format`.
+//!
+//! Parsing and filtering (`build_roas`) are identical for every format; this module only
+//! decides how the resulting [`Routes`] get serialized.
+
+use anyhow::{anyhow, Result};
+
+use crate::{Routes, ROA};
+
+#[derive(Clone, Copy)]
+pub(crate) enum OutputFormat {
+    Json,
+    Bird,
+    Bird2,
+    Csv,
+}
+
+impl OutputFormat {
+    pub(crate) fn from_str(s: &str) -> Result<OutputFormat> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "bird" => Ok(OutputFormat::Bird),
+            "bird2" => Ok(OutputFormat::Bird2),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(anyhow!("unknown output format: {s} (expected json, bird, bird2 or csv)")),
+        }
+    }
+}
+
+pub(crate) fn render(routes: &Routes, format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string(routes)?),
+        OutputFormat::Bird => Ok(render_bird(&routes.roas)),
+        OutputFormat::Bird2 => Ok(render_bird2(&routes.roas)),
+        OutputFormat::Csv => Ok(render_csv(&routes.roas)),
+    }
+}
+
+pub(crate) fn asn_number(asn: &str) -> &str {
+    asn.trim_start_matches("AS")
+}
+
+fn is_v6(roa: &ROA) -> bool {
+    roa.prefix.contains(':')
+}
+
+fn render_bird(roas: &[ROA]) -> String {
+    let mut out = String::new();
+
+    for roa in roas {
+        out.push_str(&format!(
+            "roa {} max {} as {};\n",
+            roa.prefix, roa.max_length, asn_number(&roa.asn)
+        ));
+    }
+
+    out
+}
+
+fn render_bird2(roas: &[ROA]) -> String {
+    let mut roa4 = String::new();
+    let mut roa6 = String::new();
+
+    for roa in roas {
+        let line = format!(
+            "\troute {} max {} as {};\n",
+            roa.prefix, roa.max_length, asn_number(&roa.asn)
+        );
+
+        if is_v6(roa) {
+            roa6.push_str(&line);
+        } else {
+            roa4.push_str(&line);
+        }
+    }
+
+    format!(
+        "roa4 table dn42_roa4 {{\n{roa4}}}\n\nroa6 table dn42_roa6 {{\n{roa6}}}\n"
+    )
+}
+
+fn render_csv(roas: &[ROA]) -> String {
+    let mut out = String::from("asn,prefix,maxLength\n");
+
+    for roa in roas {
+        out.push_str(&format!(
+            "{},{},{}\n",
+            asn_number(&roa.asn), roa.prefix, roa.max_length
+        ));
+    }
+
+    out
+}
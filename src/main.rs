@@ -1,27 +1,38 @@
 use std::{env, fs, io, net::IpAddr, time::{SystemTime, UNIX_EPOCH}};
 
 use anyhow::{anyhow, Result};
+use rayon::prelude::*;
 use serde::Serialize;
 
+mod conflict;
+mod format;
+mod rtr;
+mod trie;
+
+use format::OutputFormat;
+use trie::{Filter, FilterTrie};
+
 #[derive(Serialize)]
 struct Metadata {
     counts: usize,
     generated: u64,
     valid: u64,
+    duplicates: usize,
+    conflicts: usize,
 }
 
-#[derive(Serialize)]
-struct ROA {
-    prefix: String,
+#[derive(Clone, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct ROA {
+    pub(crate) prefix: String,
     #[serde(rename = "maxLength")]
-    max_length: u8,
-    asn: String,
+    pub(crate) max_length: u8,
+    pub(crate) asn: String,
 }
 
 #[derive(Serialize)]
-struct Routes {
+pub(crate) struct Routes {
     metadata: Metadata,
-    roas: Vec<ROA>,
+    pub(crate) roas: Vec<ROA>,
 }
 
 struct CIDR {
@@ -46,54 +57,66 @@ impl CIDR {
             Err(_) => return Err(anyhow!("invalid CIDR: {s}")),
         };
 
+        if netmask > max_netmask(&ip) {
+            return Err(anyhow!("invalid CIDR: {s} (netmask exceeds address length)"));
+        }
+
         Ok(CIDR {
             ip,
             netmask,
         })
     }
 
-    fn contains(&self, ip: &IpAddr) -> bool {
-        match (&self.ip, ip) {
-            (IpAddr::V4(a), IpAddr::V4(b)) => {
-                let a = u32::from(*a);
-                let b = u32::from(*b);
-
-                a >> (32 - self.netmask) == b >> (32 - self.netmask)
-            },
-            (IpAddr::V6(a), IpAddr::V6(b)) => {
-                let a = u128::from(*a);
-                let b = u128::from(*b);
+}
 
-                a >> (128 - self.netmask) == b >> (128 - self.netmask)
-            },
-            (IpAddr::V4(_), IpAddr::V6(_)) => false,
-            (IpAddr::V6(_), IpAddr::V4(_)) => false,
-        }
+/// The number of address bits for `ip`'s family, i.e. the widest valid prefix length.
+fn max_netmask(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
     }
 }
 
 fn main() -> Result<()> {
-    let args: Vec<_> = env::args().collect();
+    let mut args: Vec<_> = env::args().collect();
 
-    if args.len() != 3 {
-        return Err(anyhow!("Usage: {} registry route.json", args[0]));
+    if args.len() == 4 && args[1] == "serve" {
+        return rtr::serve(&args[2], &args[3]);
     }
 
-    let mut filters = vec![];
+    let mut output_format = OutputFormat::Json;
+    if let Some(i) = args.iter().position(|a| a == "--format") {
+        if i + 1 >= args.len() {
+            return Err(anyhow!("--format requires a value"));
+        }
 
-    let filter = format!("{}/data/filter.txt", args[1]);
-    process_filter(&filter, &mut filters)?;
+        output_format = OutputFormat::from_str(&args[i + 1])?;
+        args.drain(i..=i + 1);
+    }
 
-    let filter = format!("{}/data/filter6.txt", args[1]);
-    process_filter(&filter, &mut filters)?;
+    let strict = if let Some(i) = args.iter().position(|a| a == "--strict") {
+        args.remove(i);
+        true
+    } else {
+        false
+    };
 
-    let mut roas = vec![];
+    if args.len() != 3 {
+        return Err(anyhow!(
+            "Usage: {} [--format json|bird|bird2|csv] [--strict] registry route.json\n       {} serve registry listen-addr",
+            args[0], args[0]
+        ));
+    }
 
-    let path = format!("{}/data/route", args[1]);
-    process_directory(&path, &mut roas, &filters)?;
+    let roas = build_roas(&args[1])?;
 
-    let path = format!("{}/data/route6", args[1]);
-    process_directory(&path, &mut roas, &filters)?;
+    let report = conflict::analyze(&roas);
+    if strict && (report.duplicates > 0 || report.conflicts > 0) {
+        return Err(anyhow!(
+            "found {} duplicate(s) and {} conflict(s) in strict mode",
+            report.duplicates, report.conflicts
+        ));
+    }
 
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
     let expire = now + 7 * 24 * 60 * 60;
@@ -102,6 +125,8 @@ fn main() -> Result<()> {
         counts: roas.len(),
         generated: now,
         valid: expire,
+        duplicates: report.duplicates,
+        conflicts: report.conflicts,
     };
 
     let routes = Routes {
@@ -109,15 +134,38 @@ fn main() -> Result<()> {
         roas,
     };
 
-    let output = serde_json::to_string(&routes)?;
+    let output = format::render(&routes, output_format)?;
     fs::write(&args[2], output)?;
 
     Ok(())
 }
 
+/// Parses the filters and route directories under `registry` into the validated ROA set.
+pub(crate) fn build_roas(registry: &str) -> Result<Vec<ROA>> {
+    let mut filters = FilterTrie::new();
+
+    let filter = format!("{registry}/data/filter.txt");
+    process_filter(&filter, &mut filters)?;
+
+    let filter = format!("{registry}/data/filter6.txt");
+    process_filter(&filter, &mut filters)?;
+
+    let mut roas = vec![];
+
+    let path = format!("{registry}/data/route");
+    process_directory(&path, &mut roas, &filters)?;
+
+    let path = format!("{registry}/data/route6");
+    process_directory(&path, &mut roas, &filters)?;
+
+    roas.sort();
+
+    Ok(roas)
+}
+
 fn process_filter(
     path: &str,
-    filters: &mut Vec<(CIDR, bool, u8, u8)>
+    filters: &mut FilterTrie
 ) -> Result<()> {
     let filter = fs::read_to_string(path)?;
 
@@ -159,7 +207,9 @@ fn process_filter(
             Err(_) => continue,
         };
 
-        filters.push((cidr, allow, min, max));
+        if filters.insert(cidr.ip, cidr.netmask, Filter { allow, min, max }).is_err() {
+            continue;
+        }
     }
 
     Ok(())
@@ -168,28 +218,29 @@ fn process_filter(
 fn process_directory(
     path: &str,
     roas: &mut Vec<ROA>,
-    filters: &Vec<(CIDR, bool, u8, u8)>
+    filters: &FilterTrie
 ) -> Result<()> {
-    let files = fs::read_dir(path)?;
+    let files: Vec<_> = fs::read_dir(path)?.collect();
 
-    for file in files {
-        let roa = match process_entry(file, &filters) {
-            Ok(roa) => roa,
+    let processed: Vec<Vec<ROA>> = files
+        .into_par_iter()
+        .filter_map(|file| match process_entry(file, filters) {
+            Ok(roa) => Some(roa),
             Err(e) => {
                 eprintln!("Failed to process: {e}. ");
-                continue;
+                None
             },
-        };
+        })
+        .collect();
 
-        roas.extend(roa);
-    }
+    roas.extend(processed.into_iter().flatten());
 
     Ok(())
 }
 
 fn process_entry(
     file: Result<fs::DirEntry, io::Error>,
-    filters: &Vec<(CIDR, bool, u8, u8)>
+    filters: &FilterTrie
 ) -> Result<Vec<ROA>> {
     let file = file?.path();
     let file = fs::read_to_string(file)?;
@@ -237,35 +288,30 @@ fn process_entry(
     let addr: IpAddr = prefix_parts[0].parse()?;
     let netmask: u8 = prefix_parts[1].parse()?;
 
-    let mut filter: Option<(u8, u8)> = None;
-
-    for f in filters {
-        if f.0.contains(&addr) {
-            if !f.1 {
-                return Ok(vec![]);
-            }
-
-            filter = Some((f.2, f.3));
-            break;
-        }
+    if netmask > max_netmask(&addr) {
+        return Err(anyhow!("invalid CIDR: {prefix} (netmask exceeds address length)"));
     }
 
-    let filter = match filter {
+    let filter = match filters.lookup(&addr) {
         Some(f) => f,
         None => return Err(anyhow!("IP {addr} is in an invalid range")),
     };
 
+    if !filter.allow {
+        return Ok(vec![]);
+    }
+
     let max_length = match max_length {
         Some(max_length) => {
-            if max_length > filter.1 {
-                filter.1
-            } else if max_length < filter.0 {
-                filter.0
+            if max_length > filter.max {
+                filter.max
+            } else if max_length < filter.min {
+                filter.min
             } else {
                 max_length
             }
         },
-        None => filter.1,
+        None => filter.max,
     };
 
     if netmask > max_length {
@@ -0,0 +1,230 @@
+//! Serves the generated ROA set to routers over the RPKI-to-Router protocol (RFC 6810).
+//!
+//! This is a first cut: every Serial Query is answered the same way as a Reset Query
+//! (full Cache Response + all Prefix PDUs + End Of Data), which is always a valid RTR
+//! response even though it skips the incremental Serial Query fast path.
+
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{build_roas, format::asn_number, ROA};
+
+const PROTOCOL_VERSION: u8 = 0;
+
+const PDU_SERIAL_QUERY: u8 = 1;
+const PDU_RESET_QUERY: u8 = 2;
+const PDU_CACHE_RESPONSE: u8 = 3;
+const PDU_IPV4_PREFIX: u8 = 4;
+const PDU_IPV6_PREFIX: u8 = 6;
+const PDU_END_OF_DATA: u8 = 7;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+struct Cache {
+    session_id: u16,
+    serial: u32,
+    roas: Vec<ROA>,
+}
+
+/// Builds the ROA set once, then serves it over RTR on `listen_addr`, re-reading
+/// `registry` every [`REFRESH_INTERVAL`] and bumping the serial when it changes.
+pub fn serve(registry: &str, listen_addr: &str) -> Result<()> {
+    let cache = Arc::new(Mutex::new(Cache {
+        session_id: session_id(),
+        serial: 0,
+        roas: build_roas(registry)?,
+    }));
+
+    {
+        let cache = Arc::clone(&cache);
+        let registry = registry.to_owned();
+        thread::spawn(move || refresh_loop(registry, cache));
+    }
+
+    let listener = TcpListener::bind(listen_addr)?;
+    println!("rtr: listening on {listen_addr}");
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("rtr: failed to accept connection: {e}");
+                continue;
+            },
+        };
+
+        let cache = Arc::clone(&cache);
+        thread::spawn(move || {
+            if let Err(e) = handle_client(stream, cache) {
+                eprintln!("rtr: client error: {e}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn refresh_loop(registry: String, cache: Arc<Mutex<Cache>>) {
+    loop {
+        thread::sleep(REFRESH_INTERVAL);
+
+        let roas = match build_roas(&registry) {
+            Ok(roas) => roas,
+            Err(e) => {
+                eprintln!("rtr: failed to refresh registry: {e}");
+                continue;
+            },
+        };
+
+        let mut cache = cache.lock().unwrap();
+        if roas != cache.roas {
+            cache.serial = cache.serial.wrapping_add(1);
+            cache.roas = roas;
+        }
+    }
+}
+
+fn session_id() -> u16 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    now.subsec_nanos() as u16
+}
+
+// Wire sizes per RFC 6810: header (8 bytes) plus any trailing fields.
+const SERIAL_QUERY_LEN: u32 = 12;
+const RESET_QUERY_LEN: u32 = 8;
+
+fn handle_client(mut stream: TcpStream, cache: Arc<Mutex<Cache>>) -> Result<()> {
+    loop {
+        let mut header = [0u8; 8];
+        if let Err(e) = stream.read_exact(&mut header) {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+
+        let length = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+        match header[1] {
+            PDU_SERIAL_QUERY => {
+                if length != SERIAL_QUERY_LEN {
+                    return Err(anyhow!(
+                        "malformed Serial Query: length {length} (expected {SERIAL_QUERY_LEN})"
+                    ));
+                }
+
+                let mut rest = [0u8; (SERIAL_QUERY_LEN - 8) as usize];
+                stream.read_exact(&mut rest)?;
+
+                send_full_response(&mut stream, &cache)?;
+            },
+            PDU_RESET_QUERY => {
+                if length != RESET_QUERY_LEN {
+                    return Err(anyhow!(
+                        "malformed Reset Query: length {length} (expected {RESET_QUERY_LEN})"
+                    ));
+                }
+
+                send_full_response(&mut stream, &cache)?;
+            },
+            other => return Err(anyhow!("unsupported RTR PDU type: {other}")),
+        }
+    }
+}
+
+fn send_full_response(stream: &mut TcpStream, cache: &Arc<Mutex<Cache>>) -> Result<()> {
+    let (session_id, serial, roas) = {
+        let cache = cache.lock().unwrap();
+        (cache.session_id, cache.serial, cache.roas.clone())
+    };
+
+    write_cache_response(stream, session_id)?;
+
+    for roa in &roas {
+        let pdu = match build_prefix_pdu(roa) {
+            Ok(pdu) => pdu,
+            Err(e) => {
+                eprintln!("rtr: skipping malformed ROA {} as {}: {e}", roa.prefix, roa.asn);
+                continue;
+            },
+        };
+
+        stream.write_all(&pdu)?;
+    }
+
+    write_end_of_data(stream, session_id, serial)
+}
+
+fn write_cache_response(stream: &mut TcpStream, session_id: u16) -> Result<()> {
+    let mut pdu = Vec::with_capacity(8);
+    pdu.push(PROTOCOL_VERSION);
+    pdu.push(PDU_CACHE_RESPONSE);
+    pdu.extend_from_slice(&session_id.to_be_bytes());
+    pdu.extend_from_slice(&8u32.to_be_bytes());
+
+    stream.write_all(&pdu)?;
+    Ok(())
+}
+
+/// Builds the wire bytes for a single Prefix PDU, without touching the socket, so a
+/// malformed ROA can be logged and skipped instead of aborting the whole response.
+fn build_prefix_pdu(roa: &ROA) -> Result<Vec<u8>> {
+    let parts: Vec<_> = roa.prefix.split('/').collect();
+    if parts.len() != 2 {
+        return Err(anyhow!("invalid ROA prefix: {}", roa.prefix));
+    }
+
+    let addr: IpAddr = parts[0].parse()?;
+    let prefix_length: u8 = parts[1].parse()?;
+    let asn: u32 = asn_number(&roa.asn).parse()?;
+
+    let mut pdu = Vec::with_capacity(32);
+
+    match addr {
+        IpAddr::V4(addr) => {
+            pdu.push(PROTOCOL_VERSION);
+            pdu.push(PDU_IPV4_PREFIX);
+            pdu.extend_from_slice(&[0, 0]);
+            pdu.extend_from_slice(&20u32.to_be_bytes());
+            pdu.push(1); // flags: announce
+            pdu.push(prefix_length);
+            pdu.push(roa.max_length);
+            pdu.push(0);
+            pdu.extend_from_slice(&addr.octets());
+            pdu.extend_from_slice(&asn.to_be_bytes());
+        },
+        IpAddr::V6(addr) => {
+            pdu.push(PROTOCOL_VERSION);
+            pdu.push(PDU_IPV6_PREFIX);
+            pdu.extend_from_slice(&[0, 0]);
+            pdu.extend_from_slice(&32u32.to_be_bytes());
+            pdu.push(1);
+            pdu.push(prefix_length);
+            pdu.push(roa.max_length);
+            pdu.push(0);
+            pdu.extend_from_slice(&addr.octets());
+            pdu.extend_from_slice(&asn.to_be_bytes());
+        },
+    }
+
+    Ok(pdu)
+}
+
+fn write_end_of_data(stream: &mut TcpStream, session_id: u16, serial: u32) -> Result<()> {
+    let mut pdu = Vec::with_capacity(12);
+    pdu.push(PROTOCOL_VERSION);
+    pdu.push(PDU_END_OF_DATA);
+    pdu.extend_from_slice(&session_id.to_be_bytes());
+    pdu.extend_from_slice(&12u32.to_be_bytes());
+    pdu.extend_from_slice(&serial.to_be_bytes());
+
+    stream.write_all(&pdu)?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Longest-prefix-match tries for filter lookups (one for IPv4, one for IPv6).
+//!
+//! Filters are inserted keyed by their network bits. A lookup walks from the root
+//! consuming address bits one at a time and remembers the deepest node that carries a
+//! value, so the most specific filter wins regardless of the order filters were read in.
+//! A `/0` filter lives at the root and acts as a catch-all; an address with no matching
+//! node returns `None`.
+
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Result};
+
+#[derive(Clone, Copy)]
+pub(crate) struct Filter {
+    pub(crate) allow: bool,
+    pub(crate) min: u8,
+    pub(crate) max: u8,
+}
+
+struct Node {
+    children: [Option<Box<Node>>; 2],
+    value: Option<Filter>,
+}
+
+impl Node {
+    fn new() -> Node {
+        Node {
+            children: [None, None],
+            value: None,
+        }
+    }
+}
+
+pub(crate) struct FilterTrie {
+    v4: Node,
+    v6: Node,
+}
+
+impl FilterTrie {
+    pub(crate) fn new() -> FilterTrie {
+        FilterTrie {
+            v4: Node::new(),
+            v6: Node::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, ip: IpAddr, prefix_len: u8, filter: Filter) -> Result<()> {
+        match ip {
+            IpAddr::V4(addr) => {
+                if prefix_len > 32 {
+                    return Err(anyhow!("invalid prefix length /{prefix_len} for IPv4 filter"));
+                }
+
+                let bits = u32::from(addr);
+                insert(&mut self.v4, prefix_len, filter, |i| (bits >> (31 - i)) & 1 == 1);
+            },
+            IpAddr::V6(addr) => {
+                if prefix_len > 128 {
+                    return Err(anyhow!("invalid prefix length /{prefix_len} for IPv6 filter"));
+                }
+
+                let bits = u128::from(addr);
+                insert(&mut self.v6, prefix_len, filter, |i| (bits >> (127 - i)) & 1 == 1);
+            },
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn lookup(&self, ip: &IpAddr) -> Option<Filter> {
+        match ip {
+            IpAddr::V4(addr) => {
+                let bits = u32::from(*addr);
+                lookup(&self.v4, 32, |i| (bits >> (31 - i)) & 1 == 1)
+            },
+            IpAddr::V6(addr) => {
+                let bits = u128::from(*addr);
+                lookup(&self.v6, 128, |i| (bits >> (127 - i)) & 1 == 1)
+            },
+        }
+    }
+}
+
+fn insert(root: &mut Node, prefix_len: u8, filter: Filter, bit_at: impl Fn(u8) -> bool) {
+    let mut node = root;
+
+    for i in 0..prefix_len {
+        let bit = bit_at(i) as usize;
+        node = node.children[bit].get_or_insert_with(|| Box::new(Node::new()));
+    }
+
+    node.value = Some(filter);
+}
+
+fn lookup(root: &Node, addr_len: u8, bit_at: impl Fn(u8) -> bool) -> Option<Filter> {
+    let mut node = root;
+    let mut best = node.value;
+
+    for i in 0..addr_len {
+        let bit = bit_at(i) as usize;
+
+        node = match &node.children[bit] {
+            Some(next) => next,
+            None => break,
+        };
+
+        if node.value.is_some() {
+            best = node.value;
+        }
+    }
+
+    best
+}